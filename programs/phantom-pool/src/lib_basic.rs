@@ -34,7 +34,7 @@ pub mod phantom_pool {
         order.timestamp = Clock::get()?.unix_timestamp;
         order.status = OrderStatus::Pending;
         
-        pool.order_count = pool.order_count.checked_add(1).unwrap();
+        pool.order_count = pool.order_count.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
         
         emit!(OrderSubmitted {
             trader: ctx.accounts.trader.key(),
@@ -76,7 +76,7 @@ pub mod phantom_pool {
         
         let total_volume = verify_threshold_proof(&threshold_proof, &trade_data)?;
         
-        pool.total_volume = pool.total_volume.checked_add(total_volume).unwrap();
+        pool.total_volume = pool.total_volume.checked_add(total_volume).ok_or(ErrorCode::MathOverflow)?;
         
         emit!(TradesSettled {
             batch_size: trade_data.len() as u32,
@@ -200,6 +200,8 @@ pub enum ErrorCode {
     TooManyTrades,
     #[msg("Invalid threshold proof")]
     InvalidThresholdProof,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
 }
 
 fn validate_vrf_proof(proof: &[u8; 128]) -> Result<u8> {
@@ -211,7 +213,7 @@ fn verify_threshold_proof(proof: &[u8; 256], trades: &[TradeSettlement]) -> Resu
     let mut total_volume = 0u64;
     
     for trade in trades {
-        total_volume = total_volume.checked_add(trade.volume).unwrap();
+        total_volume = total_volume.checked_add(trade.volume).ok_or(ErrorCode::MathOverflow)?;
     }
     
     let proof_hash = proof[0] as u64;
@@ -0,0 +1,34 @@
+//! Deterministic stand-ins for the proof verifiers, compiled only with the
+//! `mock-crypto` feature so integration tests can drive matching and
+//! settlement without running a prover.
+//!
+//! A mock proof is the hash of a domain tag and the verifier's public inputs,
+//! zero-padded to the proof length. It is accepted for exactly those inputs,
+//! so tests can still exercise the rejection paths with proofs made for
+//! other inputs.
+
+use anchor_lang::solana_program::hash::hashv;
+
+pub const RANGE_DOMAIN: &[u8] = b"phantom-pool/mock/range";
+pub const SOLVENCY_DOMAIN: &[u8] = b"phantom-pool/mock/solvency";
+
+/// Proof of `len` bytes that the mock verifier for `domain` accepts for `inputs`
+pub fn mock_proof(domain: &[u8], inputs: &[&[u8]], len: usize) -> Vec<u8> {
+    let mut proof = digest(domain, inputs).to_vec();
+    proof.resize(len.max(proof.len()), 0);
+    proof
+}
+
+/// Whether `proof` is the mock proof for `inputs` under `domain`
+pub fn accepts(proof: &[u8], domain: &[u8], inputs: &[&[u8]]) -> bool {
+    proof.len() >= 32
+        && proof[..32] == digest(domain, inputs)
+        && proof[32..].iter().all(|b| *b == 0)
+}
+
+fn digest(domain: &[u8], inputs: &[&[u8]]) -> [u8; 32] {
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(inputs.len() + 1);
+    parts.push(domain);
+    parts.extend_from_slice(inputs);
+    hashv(&parts).to_bytes()
+}